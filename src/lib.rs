@@ -1,9 +1,16 @@
-use proc_macro2::TokenStream;
+use std::collections::HashSet;
+
+use proc_macro2::{Span, TokenStream};
+use proc_macro_crate::{crate_name, FoundCrate};
 use quote::{quote, quote_spanned};
+use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
-use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, GenericParam, Generics};
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Fields, GenericParam, Generics, Ident, Path,
+    Token, Type, WherePredicate,
+};
 
-#[proc_macro_derive(CdrEncodingSize)]
+#[proc_macro_derive(CdrEncodingSize, attributes(cdr_encoding_size))]
 pub fn derive_cdr_encoding_size(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     // Parse the input tokens into a syntax tree.
     let input = parse_macro_input!(input as DeriveInput);
@@ -11,17 +18,33 @@ pub fn derive_cdr_encoding_size(input: proc_macro::TokenStream) -> proc_macro::T
     // Used in the quasi-quotation below as `#name`.
     let name = input.ident;
 
-    // Add a bound `T: HeapSize` to every type parameter T.
-    let generics = add_trait_bounds(input.generics);
+    // Resolve the support crate once, so the generated paths keep working even
+    // when the caller renames or re-exports it.
+    let krate = resolve_crate(&input.attrs);
+
+    // Decide the generic bounds: an explicit `#[cdr_encoding_size(bound =
+    // "...")]` replaces the auto-generated predicates wholesale, otherwise we
+    // bound only the type parameters that actually reach the size sum.
+    let generics = match parse_bound_attr(&input.attrs) {
+        Some(predicates) => {
+            let mut generics = input.generics;
+            generics
+                .make_where_clause()
+                .predicates
+                .extend(predicates);
+            generics
+        }
+        None => add_trait_bounds(input.generics, &krate, &input.data),
+    };
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    // Generate an expression to sum up the heap size of each field.
-    let sum = cdr_size_sum(&input.data);
+    // Generate an expression to sum up the encoded size of each field.
+    let sum = cdr_size_sum(&input.data, &krate);
 
     let expanded = quote! {
         // The generated impl.
-        impl #impl_generics cdr_encoding_size::CdrEncodingSize for #name #ty_generics #where_clause {
-            fn cdr_encoding_max_size() -> cdr_encoding_size::CdrEncodingMaxSize {
+        impl #impl_generics #krate::CdrEncodingSize for #name #ty_generics #where_clause {
+            fn cdr_encoding_max_size() -> #krate::CdrEncodingMaxSize {
                 #sum
             }
         }
@@ -31,18 +54,172 @@ pub fn derive_cdr_encoding_size(input: proc_macro::TokenStream) -> proc_macro::T
     proc_macro::TokenStream::from(expanded)
 }
 
-// Add a bound `T: HeapSize` to every type parameter T.
-fn add_trait_bounds(mut generics: Generics) -> Generics {
+// Determine the identifier to use for the support crate. An explicit
+// `#[cdr_encoding_size(crate = path::to::crate)]` wins; otherwise we ask
+// `proc-macro-crate` for the name the dependency is known by, falling back to
+// `cdr_encoding_size` both when the derive is used from within the crate
+// itself and when detection fails.
+fn resolve_crate(attrs: &[syn::Attribute]) -> Path {
+    for attr in attrs {
+        if !attr.path().is_ident("cdr_encoding_size") {
+            continue;
+        }
+        let mut path = None;
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                path = Some(meta.value()?.parse::<Path>()?);
+            } else if meta.input.peek(Token![=]) {
+                // Skip the value of other keys (e.g. `bound`) so parsing can
+                // continue past them.
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+            Ok(())
+        });
+        if let Some(path) = path {
+            return path;
+        }
+    }
+
+    let ident = match crate_name("cdr-encoding-size") {
+        Ok(FoundCrate::Name(name)) => Ident::new(&name, Span::call_site()),
+        Ok(FoundCrate::Itself) | Err(_) => Ident::new("cdr_encoding_size", Span::call_site()),
+    };
+    parse_quote!(#ident)
+}
+
+// Parse an explicit `#[cdr_encoding_size(bound = "T: Foo, U: Bar")]`
+// container attribute into a set of where-predicates that should replace the
+// auto-generated bounds entirely.
+fn parse_bound_attr(attrs: &[syn::Attribute]) -> Option<Punctuated<WherePredicate, Token![,]>> {
+    for attr in attrs {
+        if !attr.path().is_ident("cdr_encoding_size") {
+            continue;
+        }
+        let mut bound = None;
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("bound") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                bound = Some(lit.parse_with(Punctuated::parse_terminated)?);
+            } else if meta.input.peek(Token![=]) {
+                // Skip the value of other keys (e.g. `crate`) so parsing can
+                // continue past them.
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+            Ok(())
+        });
+        if bound.is_some() {
+            return bound;
+        }
+    }
+    None
+}
+
+// Add a bound `T: CdrEncodingSize` to every type parameter that contributes to
+// the size sum. Parameters that only appear behind `PhantomData` (or not at
+// all) are left untouched, so generic types that currently fail to compile —
+// most commonly a `PhantomData<T>` field — derive cleanly.
+fn add_trait_bounds(mut generics: Generics, krate: &Path, data: &Data) -> Generics {
+    let params: HashSet<Ident> = generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(type_param) => Some(type_param.ident.clone()),
+            _ => None,
+        })
+        .collect();
+
+    let mut used = HashSet::new();
+    for ty in contributing_types(data) {
+        collect_used_params(ty, &params, &mut used);
+    }
+
     for param in &mut generics.params {
         if let GenericParam::Type(ref mut type_param) = *param {
-            type_param.bounds.push(parse_quote!(cdr_encoding_size::CdrEncodingSize));
+            if used.contains(&type_param.ident) {
+                type_param.bounds.push(parse_quote!(#krate::CdrEncodingSize));
+            }
         }
     }
     generics
 }
 
+// Every field type that contributes a term to the size sum, across both struct
+// and enum shapes.
+fn contributing_types(data: &Data) -> Vec<&Type> {
+    // Only fields that actually call `<#ty>::cdr_encoding_max_size()` impose a
+    // bound; `skip` and `bytes = N` fields do not reference their type.
+    let fields_types = |fields: &Fields| -> Vec<&Type> {
+        let keep = |f: &&syn::Field| matches!(field_override(&f.attrs), FieldOverride::Default);
+        match fields {
+            Fields::Named(fields) => fields.named.iter().filter(keep).map(|f| &f.ty).collect(),
+            Fields::Unnamed(fields) => {
+                fields.unnamed.iter().filter(keep).map(|f| &f.ty).collect()
+            }
+            Fields::Unit => Vec::new(),
+        }
+    };
+    match data {
+        Data::Struct(data) => fields_types(&data.fields),
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .flat_map(|v| fields_types(&v.fields))
+            .collect(),
+        Data::Union(_) => Vec::new(),
+    }
+}
+
+// Record which of `params` appear in `ty`, treating `PhantomData<_>` as inert
+// (its type arguments are not walked).
+fn collect_used_params(ty: &Type, params: &HashSet<Ident>, used: &mut HashSet<Ident>) {
+    match ty {
+        Type::Path(type_path) => {
+            if let Some(qself) = &type_path.qself {
+                collect_used_params(&qself.ty, params, used);
+            }
+            // A bare `T` is a use of the parameter itself.
+            if type_path.qself.is_none() {
+                if let Some(ident) = type_path.path.get_ident() {
+                    if params.contains(ident) {
+                        used.insert(ident.clone());
+                    }
+                }
+            }
+            let is_phantom = type_path
+                .path
+                .segments
+                .last()
+                .is_some_and(|seg| seg.ident == "PhantomData");
+            if is_phantom {
+                return;
+            }
+            for segment in &type_path.path.segments {
+                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+                    for arg in &args.args {
+                        if let syn::GenericArgument::Type(ty) = arg {
+                            collect_used_params(ty, params, used);
+                        }
+                    }
+                }
+            }
+        }
+        Type::Reference(r) => collect_used_params(&r.elem, params, used),
+        Type::Ptr(p) => collect_used_params(&p.elem, params, used),
+        Type::Slice(s) => collect_used_params(&s.elem, params, used),
+        Type::Array(a) => collect_used_params(&a.elem, params, used),
+        Type::Paren(p) => collect_used_params(&p.elem, params, used),
+        Type::Group(g) => collect_used_params(&g.elem, params, used),
+        Type::Tuple(t) => {
+            for elem in &t.elems {
+                collect_used_params(elem, params, used);
+            }
+        }
+        _ => {}
+    }
+}
+
 // Generate an expression to sum up the size of each field.
-fn cdr_size_sum(data: &Data) -> TokenStream {
+fn cdr_size_sum(data: &Data, krate: &Path) -> TokenStream {
     match *data {
         Data::Struct(ref data) => {
             match data.fields {
@@ -59,37 +236,110 @@ fn cdr_size_sum(data: &Data) -> TokenStream {
                     // implement `HeapSize` then the compiler's error message
                     // underlines which field it is. An example is shown in the
                     // readme of the parent directory.
-                    let recurse = fields.named.iter().map(|f| {
-                        let ty = &f.ty;
-                        quote_spanned! {f.span()=>
-                            <#ty>::cdr_encoding_max_size()
-                        }
-                    });
+                    let recurse = fields.named.iter().filter_map(|f| field_term(f, krate));
                     quote! {
-                        cdr_encoding_size::CdrEncodingMaxSize::Bytes(0) #(+ #recurse)*
+                        #krate::CdrEncodingMaxSize::Bytes(0) #(+ #recurse)*
                     }
                 }
                 Fields::Unnamed(ref fields) => {
                     // Expands to an expression like
                     //
                     //     0 + self.0.heap_size() + self.1.heap_size() + self.2.heap_size()
-                    let recurse = fields.unnamed.iter().enumerate().map(|(_i, f)| {
-                        let ty = &f.ty;
-                        //let index = Index::from(i);
-                        quote_spanned! {f.span()=>
-                            <#ty>::cdr_encoding_max_size()
-                        }
-                    });
+                    let recurse = fields.unnamed.iter().filter_map(|f| field_term(f, krate));
                     quote! {
-                        cdr_encoding_size::CdrEncodingMaxSize::Bytes(0) #(+ #recurse)*
+                        #krate::CdrEncodingMaxSize::Bytes(0) #(+ #recurse)*
                     }
                 }
                 Fields::Unit => {
                     // Unit structs cannot own more than 0 bytes of heap memory.
-                    quote!(cdr_encoding_size::CdrEncodingMaxSize::Bytes(0))
+                    quote!(#krate::CdrEncodingMaxSize::Bytes(0))
                 }
             }
         }
-        Data::Enum(_) | Data::Union(_) => unimplemented!(),
+        Data::Enum(ref data) => {
+            // CDR encodes a Rust enum like an IDL union (or enumerated type):
+            // a discriminant followed by the members of the selected variant.
+            // The maximum encoded size is therefore the discriminant plus the
+            // largest of the per-variant field sums.
+            //
+            // Each variant's sum is built exactly like the struct arms above,
+            // starting from `Bytes(0)` and adding one term per field, so a
+            // variant field whose type does not implement `CdrEncodingSize`
+            // still underlines correctly thanks to `quote_spanned!`.
+            let variants = data.variants.iter().map(|v| {
+                let recurse: Vec<TokenStream> = match v.fields {
+                    Fields::Named(ref fields) => {
+                        fields.named.iter().filter_map(|f| field_term(f, krate)).collect()
+                    }
+                    Fields::Unnamed(ref fields) => {
+                        fields.unnamed.iter().filter_map(|f| field_term(f, krate)).collect()
+                    }
+                    // Unit variants contribute only the discriminant.
+                    Fields::Unit => Vec::new(),
+                };
+                quote! {
+                    #krate::CdrEncodingMaxSize::Bytes(0) #(+ #recurse)*
+                }
+            });
+            // The discriminant defaults to a 32-bit enum in DDS, so it always
+            // costs 4 bytes. An enum with zero variants folds down to just the
+            // discriminant (`Bytes(4)`).
+            quote! {
+                #krate::CdrEncodingMaxSize::Bytes(4)
+                    + #krate::CdrEncodingMaxSize::Bytes(0)
+                        #(.combine_max(#variants))*
+            }
+        }
+        Data::Union(_) => unimplemented!(),
+    }
+}
+
+// How a single field contributes to the size sum, after consulting its
+// `#[cdr_encoding_size(...)]` attributes.
+enum FieldOverride {
+    // `skip`: the field is not serialized and contributes nothing.
+    Skip,
+    // `bytes = N`: force a fixed `Bytes(N)` contribution.
+    Bytes(syn::LitInt),
+    // No override: call `<#ty>::cdr_encoding_max_size()`.
+    Default,
+}
+
+fn field_override(attrs: &[syn::Attribute]) -> FieldOverride {
+    for attr in attrs {
+        if !attr.path().is_ident("cdr_encoding_size") {
+            continue;
+        }
+        let mut over = FieldOverride::Default;
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                over = FieldOverride::Skip;
+            } else if meta.path.is_ident("bytes") {
+                over = FieldOverride::Bytes(meta.value()?.parse()?);
+            }
+            Ok(())
+        });
+        if !matches!(over, FieldOverride::Default) {
+            return over;
+        }
+    }
+    FieldOverride::Default
+}
+
+// The size term for a single field, or `None` if the field is skipped. The
+// field's span is preserved so a non-implementing field type still underlines
+// correctly.
+fn field_term(f: &syn::Field, krate: &Path) -> Option<TokenStream> {
+    match field_override(&f.attrs) {
+        FieldOverride::Skip => None,
+        FieldOverride::Bytes(n) => Some(quote_spanned! {f.span()=>
+            #krate::CdrEncodingMaxSize::Bytes(#n)
+        }),
+        FieldOverride::Default => {
+            let ty = &f.ty;
+            Some(quote_spanned! {f.span()=>
+                <#ty>::cdr_encoding_max_size()
+            })
+        }
     }
 }
\ No newline at end of file